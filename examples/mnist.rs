@@ -0,0 +1,36 @@
+//! Trains a network on MNIST using the IDX loader and batched (`Chunk`)
+//! training.
+//!
+//! Expects the classic MNIST files to be extracted (not gzipped) into a
+//! `data/` directory under the current working directory (the crate root
+//! when run with the command below):
+//! `train-images-idx3-ubyte`, `train-labels-idx1-ubyte`.
+//!
+//!     cargo run --release --example mnist
+
+extern crate nn;
+
+use nn::{NN, HaltCondition, LearningMode};
+
+const NUM_OUTPUTS: usize = 10;
+
+fn main() {
+    let examples = nn::idx::load_examples(
+        "data/train-images-idx3-ubyte",
+        "data/train-labels-idx1-ubyte",
+        NUM_OUTPUTS,
+    ).expect("failed to load MNIST examples");
+
+    let num_inputs = examples[0].0.len() as u32;
+    let mut net = NN::new(&[num_inputs, 128, NUM_OUTPUTS as u32]);
+
+    net.train(&examples)
+        .halt_condition(HaltCondition::Epochs(10))
+        .log_interval(Some(1))
+        .learning_mode(LearningMode::Chunk)
+        .num_threads(4)
+        .chunk_size(64)
+        .rate(0.1)
+        .momentum(0.1)
+        .go();
+}
@@ -0,0 +1,86 @@
+//! Activation functions usable on a per-layer basis in an `NN`.
+
+/// An activation function applied to a layer's weighted sums.
+///
+/// Each variant knows how to compute its forward value from the
+/// pre-activation sum (`apply`) and its derivative (`derivative`), which
+/// `calculate_weight_updates` needs during backpropagation. Most
+/// derivatives are expressed in terms of the layer's output rather than
+/// its input, since that's what's already on hand while backpropagating;
+/// the exceptions (`ReLU`, `LeakyReLU`) need the raw pre-activation sum,
+/// which is why `do_run` retains it alongside the activated output.
+#[derive(Debug, Copy, Clone, PartialEq, RustcDecodable, RustcEncodable)]
+pub enum Activation {
+    /// Standard logistic sigmoid: `1 / (1 + e^-x)`, range `(0, 1)`
+    Sigmoid,
+    /// FANN-style symmetric (bipolar) sigmoid: `2 / (1 + e^-x) - 1`, range `(-1, 1)`
+    SigmoidSymmetric,
+    /// Hyperbolic tangent, range `(-1, 1)`
+    Tanh,
+    /// Rectified linear unit: `max(0, x)`
+    ReLU,
+    /// Leaky ReLU with a fixed `0.01` negative slope
+    LeakyReLU,
+    /// Identity function; useful for regression output layers
+    Linear,
+}
+
+impl Activation {
+    /// Applies the activation function to a pre-activation sum.
+    pub fn apply(&self, sum: f64) -> f64 {
+        match *self {
+            Activation::Sigmoid => 1f64 / (1f64 + (-sum).exp()),
+            Activation::SigmoidSymmetric => 2f64 / (1f64 + (-sum).exp()) - 1f64,
+            Activation::Tanh => sum.tanh(),
+            Activation::ReLU => if sum > 0f64 { sum } else { 0f64 },
+            Activation::LeakyReLU => if sum > 0f64 { sum } else { 0.01 * sum },
+            Activation::Linear => sum,
+        }
+    }
+
+    /// Derivative of the activation function with respect to its input.
+    /// `output` is the already-activated value (`apply(sum)`) and `sum`
+    /// is the pre-activation value.
+    pub fn derivative(&self, output: f64, sum: f64) -> f64 {
+        match *self {
+            Activation::Sigmoid => output * (1f64 - output),
+            Activation::SigmoidSymmetric => 0.5f64 * (1f64 - output * output),
+            Activation::Tanh => 1f64 - output * output,
+            Activation::ReLU => if sum > 0f64 { 1f64 } else { 0f64 },
+            Activation::LeakyReLU => if sum > 0f64 { 1f64 } else { 0.01 },
+            Activation::Linear => 1f64,
+        }
+    }
+
+    /// Encodes the activation as a single byte for `NN::to_bytes`.
+    pub fn to_byte(&self) -> u8 {
+        match *self {
+            Activation::Sigmoid => 0,
+            Activation::SigmoidSymmetric => 1,
+            Activation::Tanh => 2,
+            Activation::ReLU => 3,
+            Activation::LeakyReLU => 4,
+            Activation::Linear => 5,
+        }
+    }
+
+    /// Decodes an activation from a byte written by `to_byte`, or `None`
+    /// if the byte doesn't correspond to a known variant.
+    pub fn from_byte(byte: u8) -> Option<Activation> {
+        match byte {
+            0 => Some(Activation::Sigmoid),
+            1 => Some(Activation::SigmoidSymmetric),
+            2 => Some(Activation::Tanh),
+            3 => Some(Activation::ReLU),
+            4 => Some(Activation::LeakyReLU),
+            5 => Some(Activation::Linear),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Activation {
+    fn default() -> Activation {
+        Activation::Sigmoid
+    }
+}
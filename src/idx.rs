@@ -0,0 +1,154 @@
+//! Loader for the IDX binary format used by the MNIST dataset
+//! (see <http://yann.lecun.com/exdb/mnist/>), turning raw image/label
+//! files directly into the crate's native `Vec<(Vec<f64>, Vec<f64>)>`
+//! example format.
+
+use std::fs::File;
+use std::io::{self, Read, BufReader};
+use std::path::Path;
+
+const IMAGE_MAGIC: u32 = 0x00000803;
+const LABEL_MAGIC: u32 = 0x00000801;
+
+/// Reads an IDX image file, returning one flattened `u8` vector (row-major,
+/// `rows * cols` long) per image.
+pub fn read_images<P: AsRef<Path>>(path: P) -> io::Result<Vec<Vec<u8>>> {
+    let mut reader = BufReader::new(try!(File::open(path)));
+
+    let magic = try!(read_u32(&mut reader));
+    if magic != IMAGE_MAGIC {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not an IDX image file"));
+    }
+
+    let num_images = try!(read_u32(&mut reader)) as usize;
+    let num_rows = try!(read_u32(&mut reader)) as usize;
+    let num_cols = try!(read_u32(&mut reader)) as usize;
+    let image_size = num_rows * num_cols;
+
+    let mut images = Vec::with_capacity(num_images);
+    for _ in 0..num_images {
+        let mut image = vec![0u8; image_size];
+        try!(reader.read_exact(&mut image));
+        images.push(image);
+    }
+    Ok(images)
+}
+
+/// Reads an IDX label file, returning one label byte per example.
+pub fn read_labels<P: AsRef<Path>>(path: P) -> io::Result<Vec<u8>> {
+    let mut reader = BufReader::new(try!(File::open(path)));
+
+    let magic = try!(read_u32(&mut reader));
+    if magic != LABEL_MAGIC {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not an IDX label file"));
+    }
+
+    let num_labels = try!(read_u32(&mut reader)) as usize;
+    let mut labels = vec![0u8; num_labels];
+    try!(reader.read_exact(&mut labels));
+    Ok(labels)
+}
+
+/// Reads an IDX images file and its matching labels file and turns them into
+/// the crate's native example format: pixels are normalized to `[0, 1]` by
+/// dividing by `255`, and labels are one-hot encoded into a vector sized to
+/// `num_outputs` (which should match the network's output layer size).
+pub fn load_examples<P: AsRef<Path>>(images_path: P, labels_path: P, num_outputs: usize) -> io::Result<Vec<(Vec<f64>, Vec<f64>)>> {
+    let images = try!(read_images(images_path));
+    let labels = try!(read_labels(labels_path));
+
+    if images.len() != labels.len() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "image count doesn't match label count"));
+    }
+
+    let mut examples = Vec::with_capacity(images.len());
+    for (image, &label) in images.iter().zip(labels.iter()) {
+        if label as usize >= num_outputs {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "label is out of range for num_outputs"));
+        }
+        let inputs: Vec<f64> = image.iter().map(|&pixel| pixel as f64 / 255f64).collect();
+        let mut outputs = vec![0f64; num_outputs];
+        outputs[label as usize] = 1f64;
+        examples.push((inputs, outputs));
+    }
+
+    Ok(examples)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    try!(reader.read_exact(&mut buf));
+    Ok(((buf[0] as u32) << 24) | ((buf[1] as u32) << 16) | ((buf[2] as u32) << 8) | (buf[3] as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Write;
+
+    fn write_be_u32(bytes: &mut Vec<u8>, value: u32) {
+        bytes.push((value >> 24) as u8);
+        bytes.push((value >> 16) as u8);
+        bytes.push((value >> 8) as u8);
+        bytes.push(value as u8);
+    }
+
+    // builds a tiny 2x2-image IDX image file
+    fn write_images_file(path: &Path, images: &[[u8; 4]]) {
+        let mut bytes = Vec::new();
+        write_be_u32(&mut bytes, IMAGE_MAGIC);
+        write_be_u32(&mut bytes, images.len() as u32);
+        write_be_u32(&mut bytes, 2); // rows
+        write_be_u32(&mut bytes, 2); // cols
+        for image in images {
+            bytes.extend_from_slice(image);
+        }
+        let mut file = fs::File::create(path).unwrap();
+        file.write_all(&bytes).unwrap();
+    }
+
+    fn write_labels_file(path: &Path, labels: &[u8]) {
+        let mut bytes = Vec::new();
+        write_be_u32(&mut bytes, LABEL_MAGIC);
+        write_be_u32(&mut bytes, labels.len() as u32);
+        bytes.extend_from_slice(labels);
+        let mut file = fs::File::create(path).unwrap();
+        file.write_all(&bytes).unwrap();
+    }
+
+    #[test]
+    fn load_examples_round_trips_a_small_idx_file() {
+        let images_path = std::env::temp_dir().join("nn_idx_test_round_trip_images.idx");
+        let labels_path = std::env::temp_dir().join("nn_idx_test_round_trip_labels.idx");
+        write_images_file(&images_path, &[[0, 51, 102, 153], [255, 204, 153, 0]]);
+        write_labels_file(&labels_path, &[1, 0]);
+
+        let examples = load_examples(&images_path, &labels_path, 2).unwrap();
+
+        assert_eq!(examples.len(), 2);
+        assert_eq!(examples[0].0, vec![0f64, 0.2, 0.4, 0.6]);
+        assert_eq!(examples[0].1, vec![0f64, 1f64]);
+        assert_eq!(examples[1].0, vec![1f64, 0.8, 0.6, 0f64]);
+        assert_eq!(examples[1].1, vec![1f64, 0f64]);
+
+        fs::remove_file(&images_path).unwrap();
+        fs::remove_file(&labels_path).unwrap();
+    }
+
+    #[test]
+    fn load_examples_errors_instead_of_panicking_on_an_out_of_range_label() {
+        let images_path = std::env::temp_dir().join("nn_idx_test_out_of_range_images.idx");
+        let labels_path = std::env::temp_dir().join("nn_idx_test_out_of_range_labels.idx");
+        write_images_file(&images_path, &[[0, 0, 0, 0]]);
+        write_labels_file(&labels_path, &[5]);
+
+        let result = load_examples(&images_path, &labels_path, 2);
+
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        fs::remove_file(&images_path).unwrap();
+        fs::remove_file(&labels_path).unwrap();
+    }
+}
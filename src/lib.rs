@@ -63,15 +63,32 @@ extern crate simple_parallel;
 extern crate env_logger;
 #[macro_use] extern crate log;
 
+mod activation;
+mod loss;
+mod optimizer;
+mod regularization;
+mod rng;
+pub mod idx;
+
+pub use activation::Activation;
+pub use loss::Loss;
+pub use optimizer::{TrainAlgorithm, RpropParams};
+pub use regularization::Regularization;
 use simple_parallel::Pool;
-use HaltCondition::{ Epochs, MSE, Timer };
-use LearningMode::{ Incremental, Chunk };
+use HaltCondition::{ Epochs, MSE, Timer, MedianError };
+use LearningMode::{ Incremental, Chunk, MiniBatch };
 use std::iter::{Zip, Enumerate};
 use std::slice;
 use rustc_serialize::json;
 use time::{ Duration, PreciseTime };
 use rand::Rng;
 use std::sync::RwLock;
+use std::mem;
+use std::io::{self, Read, Write};
+use std::fs::File;
+use std::path::Path;
+use std::collections::BinaryHeap;
+use std::cmp::Ordering;
 
 const DEFAULT_LEARNING_RATE: f64 = 0.3f64;
 const DEFAULT_MOMENTUM: f64 = 0f64;
@@ -81,6 +98,11 @@ const DEFAULT_EPOCHS: u32 = 1000;
 /// of the model
 const DEFAULT_CHUNK_SIZE: usize = 10;
 
+// magic/version prefix for `NN::to_bytes`/`from_bytes`, so future format
+// changes (e.g. new per-layer metadata) can still be told apart and rejected
+const BINARY_MAGIC: &'static [u8; 4] = b"NNBF";
+const BINARY_VERSION: u8 = 1;
+
 /// Specifies when to stop training the network
 #[derive(Debug, Copy, Clone)]
 pub enum HaltCondition {
@@ -90,6 +112,11 @@ pub enum HaltCondition {
     MSE(f64),
     /// Train for some fixed amount of time and then halt
     Timer(Duration),
+    /// Train until the median per-example error over the most recent epoch
+    /// drops to or below this threshold. A streaming two-heap median is far
+    /// more resistant to a handful of pathological examples than `MSE`'s
+    /// arithmetic mean.
+    MedianError(f64),
 }
 
 /// Specifies which [learning mode](http://en.wikipedia.org/wiki/Backpropagation#Modes_of_learning)
@@ -102,12 +129,22 @@ pub enum LearningMode {
     ///
     /// This is useful during multi-threaded training
     Chunk,
+    /// train the network in contiguous mini-batches of the given size, updating weights once
+    /// per batch; pairs naturally with `Trainer::shuffle` for SGD-style training
+    MiniBatch(usize),
+}
+
+// the data a `Trainer` will train over: either a slice already fully in
+// memory (`NN::train`) or a, possibly unbounded, stream that `go()` reduces
+// to a bounded in-memory reservoir before training (`NN::train_stream`)
+enum TrainingData<'b> {
+    Slice(&'b [(Vec<f64>, Vec<f64>)]),
+    Stream(Box<Iterator<Item = (Vec<f64>, Vec<f64>)> + 'b>),
 }
 
 /// Used to specify options that dictate how a network will be trained
-#[derive(Debug)]
 pub struct Trainer<'a,'b> {
-    examples: &'b [(Vec<f64>, Vec<f64>)],
+    data: TrainingData<'b>,
     rate: f64,
     momentum: f64,
     log_interval: Option<u32>,
@@ -118,6 +155,15 @@ pub struct Trainer<'a,'b> {
     /// number of iterations to process in parallel before updating weights
     /// useful in multi-threading
     chunk_size: usize,
+    loss: Loss,
+    train_algorithm: TrainAlgorithm,
+    regularization: Regularization,
+    shuffle: bool,
+    on_epoch: Option<Box<FnMut(u32, f64)>>,
+    on_error: Option<Box<FnMut(f64)>>,
+    seed: Option<u64>,
+    reservoir_size: Option<usize>,
+    dropout: f64,
 }
 
 /// `Trainer` is used to chain together options that specify how to train a network.
@@ -143,7 +189,7 @@ impl<'a,'b> Trainer<'a,'b>  {
         self
     }
 
-    /// Specifies how often (measured in batches) to log the current error rate (mean squared error) during training.
+    /// Specifies how often (measured in batches) to log the current error rate (per the active `Loss`) during training.
     /// `Some(x)` means log after every `x` batches and `None` means never log
     pub fn log_interval(&mut self, log_interval: Option<u32>) -> &mut Trainer<'a,'b> {
         match log_interval {
@@ -166,6 +212,7 @@ impl<'a,'b> Trainer<'a,'b>  {
         match halt_condition {
             Epochs(epochs) => assert!(epochs > 0, "must train for at least one epoch"),
             MSE(mse) => assert!(mse > 0.0, "MSE must be greater than 0"),
+            MedianError(threshold) => assert!(threshold > 0.0, "median error threshold must be greater than 0"),
             _ => {}
         }
 
@@ -191,12 +238,104 @@ impl<'a,'b> Trainer<'a,'b>  {
         self
     }
 
+    /// Specifies the loss function used both to report the training error
+    /// and to compute the output layer's gradient (default is `Loss::MeanSquared`)
+    pub fn loss(&mut self, loss: Loss) -> &mut Trainer<'a,'b> {
+        self.loss = loss;
+        self
+    }
+
+    /// Specifies the training algorithm to use (default is `TrainAlgorithm::Backprop`,
+    /// i.e. the rate/momentum SGD driven by `learning_mode`). `TrainAlgorithm::Rprop`
+    /// is a full-batch method and ignores `rate`/`momentum`.
+    pub fn train_algorithm(&mut self, train_algorithm: TrainAlgorithm) -> &mut Trainer<'a,'b> {
+        self.train_algorithm = train_algorithm;
+        self
+    }
+
+    /// Specifies L1/L2 weight regularization to apply during training
+    /// (default is `Regularization::None`). Only affects `TrainAlgorithm::Backprop`.
+    pub fn regularization(&mut self, regularization: Regularization) -> &mut Trainer<'a,'b> {
+        self.regularization = regularization;
+        self
+    }
+
+    /// When `true`, permutes the order of training examples at the start of
+    /// every epoch of `Incremental` or `MiniBatch` training instead of
+    /// presenting them in a fixed order (default is `false`). Strictly-ordered
+    /// training can cycle and bias the weights toward the last examples seen.
+    pub fn shuffle(&mut self, shuffle: bool) -> &mut Trainer<'a,'b> {
+        self.shuffle = shuffle;
+        self
+    }
+
+    /// Registers a callback invoked at each `log_interval` during training
+    /// (any `LearningMode`/`TrainAlgorithm`) with the current epoch number
+    /// and training error, in place of the `error rate: ...` line this
+    /// crate used to print to stdout unconditionally.
+    pub fn on_epoch<F: FnMut(u32, f64) + 'static>(&mut self, callback: F) -> &mut Trainer<'a,'b> {
+        self.on_epoch = Some(Box::new(callback));
+        self
+    }
+
+    /// Registers a callback invoked at each `log_interval` during training
+    /// (any `LearningMode`/`TrainAlgorithm`) with just the current training
+    /// error.
+    pub fn on_error<F: FnMut(f64) + 'static>(&mut self, callback: F) -> &mut Trainer<'a,'b> {
+        self.on_error = Some(Box::new(callback));
+        self
+    }
+
+    /// Seeds the RNG used for every stochastic training step (`shuffle`,
+    /// `dropout` and `train_stream`'s reservoir sampling) so that repeated
+    /// runs are reproducible. Without it, a fresh seed is drawn from the
+    /// global thread RNG on every `go()`.
+    pub fn seed(&mut self, seed: u64) -> &mut Trainer<'a,'b> {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Specifies the capacity `k` of the in-memory reservoir `train_stream`
+    /// draws from the underlying stream via Vitter's Algorithm R. Required
+    /// when training via `train_stream`; has no effect on `train`.
+    pub fn reservoir_size(&mut self, reservoir_size: usize) -> &mut Trainer<'a,'b> {
+        assert!(reservoir_size > 0, "reservoir size must be a positive number");
+
+        self.reservoir_size = Some(reservoir_size);
+        self
+    }
+
+    /// Zeroes each hidden-unit activation independently with probability
+    /// `rate` during every training forward pass (inverted dropout:
+    /// surviving activations are scaled by `1/(1-rate)`, so no rescaling is
+    /// needed at inference time), redrawing the mask fresh for every
+    /// example or mini-batch from the network's seeded RNG. Has no effect
+    /// on `run()`. Default is `0.0` (no dropout). Only applies to
+    /// `Incremental`/`MiniBatch` training.
+    pub fn dropout(&mut self, rate: f64) -> &mut Trainer<'a,'b> {
+        assert!(rate >= 0.0 && rate < 1.0, "dropout rate must be in [0, 1)");
+
+        self.dropout = rate;
+        self
+    }
+
     /// When `go` is called, the network will begin training based on the
     /// options specified. If `go` does not get called, the network will not
     /// get trained!
     pub fn go(&mut self) -> f64 {
+        let sampled;
+        let examples: &[(Vec<f64>, Vec<f64>)] = match self.data {
+            TrainingData::Slice(examples) => examples,
+            TrainingData::Stream(ref mut stream) => {
+                let k = self.reservoir_size.expect("reservoir_size must be set when training via train_stream");
+                let mut rng = rng::seeded_rng(self.seed.unwrap_or_else(rng::random_seed));
+                sampled = reservoir_sample(stream, k, &mut rng);
+                &sampled
+            },
+        };
+
         self.nn.train_details(
-            self.examples,
+            examples,
             self.rate,
             self.momentum,
             self.log_interval,
@@ -204,6 +343,14 @@ impl<'a,'b> Trainer<'a,'b>  {
             self.num_threads,
             self.learning_mode,
             self.chunk_size,
+            self.loss,
+            self.train_algorithm,
+            self.regularization,
+            self.shuffle,
+            self.on_epoch.take(),
+            self.on_error.take(),
+            self.seed,
+            self.dropout,
         )
     }
 
@@ -214,6 +361,9 @@ impl<'a,'b> Trainer<'a,'b>  {
 pub struct NN {
     layers: Vec<Vec<Vec<f64>>>,
     num_inputs: u32,
+    /// activation function used by each layer in `layers` (i.e. excluding
+    /// the input layer); defaults to `Activation::Sigmoid` everywhere
+    activations: Vec<Activation>,
 }
 
 impl NN {
@@ -224,7 +374,19 @@ impl NN {
     /// last are hidden layers. There must be at least two layers in the network.
     pub fn new(layers_sizes: &[u32]) -> NN {
         let mut rng = rand::thread_rng();
+        NN::new_with_rng(layers_sizes, &mut rng)
+    }
+
+    /// Like `NN::new`, but draws its initial weights from a seeded PRNG
+    /// instead of the global thread RNG, so repeated calls with the same
+    /// `seed` produce bit-identical networks. Useful for regression tests
+    /// and for pinning benchmark timing against RNG jitter.
+    pub fn new_seeded(layers_sizes: &[u32], seed: u64) -> NN {
+        let mut rng = rng::seeded_rng(seed);
+        NN::new_with_rng(layers_sizes, &mut rng)
+    }
 
+    fn new_with_rng<R: Rng>(layers_sizes: &[u32], rng: &mut R) -> NN {
         assert!(layers_sizes.len() >= 2, "must have atleast two layers");
 
         assert!(layers_sizes.iter().find(|x| &&0 == x).is_none(), "can't have any empty layers");
@@ -252,7 +414,16 @@ impl NN {
             prev_layer_size = layer_size;
         }
         layers.shrink_to_fit();
-        NN { layers: layers, num_inputs: first_layer_size }
+        let activations = vec![Activation::default(); layers.len()];
+        NN { layers: layers, num_inputs: first_layer_size, activations: activations }
+    }
+
+    /// Sets the activation function used by a single layer (builder style).
+    /// `layer_idx` is `0` for the first hidden layer, up to `layers.len()-1`
+    /// for the output layer. Every layer defaults to `Activation::Sigmoid`.
+    pub fn activation(mut self, layer_idx: usize, activation: Activation) -> NN {
+        self.activations[layer_idx] = activation;
+        self
     }
 
     /// Runs the network on an input and returns a vector of the results.
@@ -263,7 +434,7 @@ impl NN {
         if inputs.len() as u32 != self.num_inputs {
             panic!("input has a different length than the network's input layer");
         }
-        self.do_run(inputs).pop().unwrap()
+        self.do_run(inputs).0.pop().unwrap()
     }
 
     /// Takes in vector of examples and returns a `Trainer` struct that is used
@@ -271,8 +442,24 @@ impl NN {
     /// No actual training will occur until the `go()` method on the
     /// `Trainer` struct is called.
     pub fn train<'b>(&'b mut self, examples: &'b [(Vec<f64>, Vec<f64>)]) -> Trainer {
+        self.trainer(TrainingData::Slice(examples))
+    }
+
+    /// Like `train`, but takes an `Iterator` of examples of unknown
+    /// (possibly unbounded) length instead of requiring the whole data set
+    /// in memory up front. `go()` draws a uniform sample of the stream into
+    /// a bounded in-memory reservoir (see `Trainer::reservoir_size`, which
+    /// must be set) via Vitter's Algorithm R, then trains over that
+    /// reservoir as `train` would.
+    pub fn train_stream<'b, I>(&'b mut self, stream: I) -> Trainer
+        where I: Iterator<Item = (Vec<f64>, Vec<f64>)> + 'b
+    {
+        self.trainer(TrainingData::Stream(Box::new(stream)))
+    }
+
+    fn trainer<'b>(&'b mut self, data: TrainingData<'b>) -> Trainer {
         Trainer {
-            examples: examples,
+            data: data,
             rate: DEFAULT_LEARNING_RATE,
             momentum: DEFAULT_MOMENTUM,
             log_interval: None,
@@ -281,6 +468,15 @@ impl NN {
             nn: self,
             num_threads: 1,
             chunk_size: DEFAULT_CHUNK_SIZE,
+            loss: Loss::default(),
+            train_algorithm: TrainAlgorithm::default(),
+            regularization: Regularization::default(),
+            shuffle: false,
+            on_epoch: None,
+            on_error: None,
+            seed: None,
+            reservoir_size: None,
+            dropout: 0.0,
         }
     }
 
@@ -295,9 +491,124 @@ impl NN {
         network
     }
 
+    /// Encodes the network into this crate's compact binary format: a
+    /// small magic/version header followed by the layer-size and
+    /// activation metadata needed to reconstruct the network's shape,
+    /// then the raw little-endian `f64` weights. Much smaller and lossless
+    /// compared to `to_json`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(BINARY_MAGIC);
+        bytes.push(BINARY_VERSION);
+
+        let layers_sizes = self.layers_sizes();
+        write_u32(&mut bytes, layers_sizes.len() as u32);
+        for &size in layers_sizes.iter() {
+            write_u32(&mut bytes, size);
+        }
+
+        for &activation in self.activations.iter() {
+            bytes.push(activation.to_byte());
+        }
+
+        for layer in self.layers.iter() {
+            for node in layer.iter() {
+                for &weight in node.iter() {
+                    write_f64(&mut bytes, weight);
+                }
+            }
+        }
+
+        bytes
+    }
+
+    /// Decodes a network from the binary format written by `to_bytes`,
+    /// validating that the encoded layer shapes are self-consistent
+    /// before returning.
+    pub fn from_bytes(bytes: &[u8]) -> Result<NN, String> {
+        if bytes.len() < 5 || &bytes[0..4] != &BINARY_MAGIC[..] {
+            return Err("not an NN binary file".to_string());
+        }
+        let version = bytes[4];
+        if version != BINARY_VERSION {
+            return Err(format!("unsupported NN binary format version: {}", version));
+        }
+        let mut cursor = 5usize;
+
+        let num_sizes = try!(read_u32(bytes, &mut cursor)) as usize;
+        let mut layers_sizes = Vec::with_capacity(num_sizes);
+        for _ in 0..num_sizes {
+            layers_sizes.push(try!(read_u32(bytes, &mut cursor)));
+        }
+        if layers_sizes.len() < 2 || layers_sizes.iter().any(|&size| size == 0) {
+            return Err("invalid layer sizes".to_string());
+        }
+
+        let num_layers = layers_sizes.len() - 1;
+        let mut activations = Vec::with_capacity(num_layers);
+        for _ in 0..num_layers {
+            if cursor >= bytes.len() {
+                return Err("truncated activations".to_string());
+            }
+            let activation = try!(Activation::from_byte(bytes[cursor]).ok_or_else(|| "unknown activation code".to_string()));
+            activations.push(activation);
+            cursor += 1;
+        }
+
+        let mut layers = Vec::with_capacity(num_layers);
+        let mut prev_layer_size = layers_sizes[0];
+        for &layer_size in layers_sizes[1..].iter() {
+            let mut layer = Vec::with_capacity(layer_size as usize);
+            for _ in 0..layer_size {
+                let mut node = Vec::with_capacity((prev_layer_size + 1) as usize);
+                for _ in 0..(prev_layer_size + 1) {
+                    node.push(try!(read_f64(bytes, &mut cursor)));
+                }
+                layer.push(node);
+            }
+            layers.push(layer);
+            prev_layer_size = layer_size;
+        }
+
+        if cursor != bytes.len() {
+            return Err("trailing bytes after network data".to_string());
+        }
+
+        Ok(NN { layers: layers, num_inputs: layers_sizes[0], activations: activations })
+    }
+
+    /// Writes the network to `path` in this crate's binary format (see `to_bytes`).
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = try!(File::create(path));
+        file.write_all(&self.to_bytes())
+    }
+
+    /// Reads a network previously written by `save`/`to_bytes`.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<NN> {
+        let mut file = try!(File::open(path));
+        let mut bytes = Vec::new();
+        try!(file.read_to_end(&mut bytes));
+        NN::from_bytes(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    // the layer sizes needed to reconstruct this network's shape, i.e.
+    // `[num_inputs, layers[0].len(), layers[1].len(), ...]` -- the same
+    // shape of slice `NN::new` expects
+    fn layers_sizes(&self) -> Vec<u32> {
+        let mut sizes = Vec::with_capacity(self.layers.len() + 1);
+        sizes.push(self.num_inputs);
+        for layer in self.layers.iter() {
+            sizes.push(layer.len() as u32);
+        }
+        sizes
+    }
+
     fn train_details(&mut self, examples: &[(Vec<f64>, Vec<f64>)], rate: f64, momentum: f64,
                      log_interval: Option<u32>, halt_condition: HaltCondition, num_threads: usize,
-                     learning_mode: LearningMode, chunk_size: usize) -> f64 {
+                     learning_mode: LearningMode, chunk_size: usize, loss: Loss,
+                     train_algorithm: TrainAlgorithm, regularization: Regularization, shuffle: bool,
+                     on_epoch: Option<Box<FnMut(u32, f64)>>, on_error: Option<Box<FnMut(f64)>>,
+                     seed: Option<u64>, dropout: f64) -> f64 {
         // check that input and output sizes are correct
         let input_layer_size = self.num_inputs;
         let output_layer_size = self.layers[self.layers.len() - 1].len();
@@ -310,27 +621,128 @@ impl NN {
             }
         }
 
-        match learning_mode {
-            Incremental => {
-                assert!(num_threads == 1, "incremental training can only be single-threaded");
-                self.train_incremental(examples, rate, momentum, log_interval, halt_condition)
+        match train_algorithm {
+            TrainAlgorithm::Backprop => match learning_mode {
+                Incremental => {
+                    assert!(num_threads == 1, "incremental training can only be single-threaded");
+                    self.train_incremental(examples, rate, momentum, log_interval, halt_condition, loss, regularization, shuffle, on_epoch, on_error, seed, dropout)
+                },
+                Chunk => self.train_chunk(examples, rate, momentum, log_interval, halt_condition, num_threads, chunk_size, loss, regularization, on_epoch, on_error),
+                MiniBatch(batch_size) => {
+                    assert!(num_threads == 1, "mini-batch training can only be single-threaded");
+                    assert!(batch_size > 0, "mini-batch size must be a positive number");
+                    self.train_minibatch(examples, rate, momentum, log_interval, halt_condition, loss, regularization, shuffle, on_epoch, on_error, seed, batch_size, dropout)
+                },
+            },
+            TrainAlgorithm::Rprop(params) => {
+                self.train_rprop(examples, log_interval, halt_condition, num_threads, chunk_size, params, loss, on_epoch, on_error)
             },
-            Chunk => self.train_chunk(examples, rate, momentum, log_interval, halt_condition, num_threads, chunk_size),
         }
     }
 
     fn train_incremental(&mut self, examples: &[(Vec<f64>, Vec<f64>)], rate: f64, momentum: f64, log_interval: Option<u32>,
-                    halt_condition: HaltCondition) -> f64 {
+                    halt_condition: HaltCondition, loss: Loss, regularization: Regularization, shuffle: bool,
+                    mut on_epoch: Option<Box<FnMut(u32, f64)>>, mut on_error: Option<Box<FnMut(f64)>>,
+                    seed: Option<u64>, dropout: f64) -> f64 {
+        let mut prev_deltas = self.make_weights_tracker(0.0f64);
+        let mut epochs = 0u32;
+        let start_time = PreciseTime::now();
+        let mut training_error_rate = 0f64;
+        let mut median_acc = MedianAccumulator::new();
+        let mut rng = rng::seeded_rng(seed.unwrap_or_else(rng::random_seed));
+        let mut order: Vec<usize> = (0..examples.len()).collect();
+        loop {
+            if epochs > 0 {
+                // log error rate if necessary
+                match log_interval {
+                    Some(interval) if epochs % interval == 0 => {
+                        let mut handled = false;
+                        if let Some(ref mut callback) = on_epoch {
+                            callback(epochs, training_error_rate);
+                            handled = true;
+                        }
+                        if let Some(ref mut callback) = on_error {
+                            callback(training_error_rate);
+                            handled = true;
+                        }
+                        if !handled {
+                            println!("error rate: {}", training_error_rate);
+                        }
+                    },
+                    _ => (),
+                }
+
+                // check if we've met the halt condition yet
+                match halt_condition {
+                    Epochs(epochs_halt) => {
+                        if epochs == epochs_halt { break }
+                    },
+                    MSE(target_error) => {
+                        if training_error_rate <= target_error { break }
+                    },
+                    MedianError(target_error) => {
+                        if median_acc.median() <= target_error { break }
+                    },
+                    Timer(duration) => {
+                        let now = PreciseTime::now();
+                        if start_time.to(now) >= duration { break }
+                    }
+                }
+            }
+
+            if shuffle {
+                rng.shuffle(&mut order);
+            }
+
+            training_error_rate = 0f64;
+            median_acc = MedianAccumulator::new();
+            for &index in order.iter() {
+                let &(ref inputs, ref targets) = &examples[index];
+                let (results, sums, masks) = self.do_run_dropout(&inputs, dropout, &mut rng);
+                let weight_updates = self.calculate_weight_updates(&results, &sums, &targets, loss, Some(&masks));
+                let example_error = calculate_error(&results, &targets, loss);
+                training_error_rate += example_error;
+                median_acc.push(example_error);
+                self.update_weights(&weight_updates, &mut prev_deltas, rate, momentum, regularization)
+            }
+            training_error_rate += self.regularization_penalty(regularization);
+            epochs += 1;
+        }
+        training_error_rate
+    }
+
+    // single-threaded mini-batch SGD: like `train_incremental`, but accumulates
+    // the weight updates of a contiguous batch of `batch_size` (post-shuffle)
+    // examples before applying a single combined update, rather than updating
+    // after every example
+    fn train_minibatch(&mut self, examples: &[(Vec<f64>, Vec<f64>)], rate: f64, momentum: f64, log_interval: Option<u32>,
+                    halt_condition: HaltCondition, loss: Loss, regularization: Regularization, shuffle: bool,
+                    mut on_epoch: Option<Box<FnMut(u32, f64)>>, mut on_error: Option<Box<FnMut(f64)>>,
+                    seed: Option<u64>, batch_size: usize, dropout: f64) -> f64 {
         let mut prev_deltas = self.make_weights_tracker(0.0f64);
         let mut epochs = 0u32;
         let start_time = PreciseTime::now();
         let mut training_error_rate = 0f64;
+        let mut median_acc = MedianAccumulator::new();
+        let mut rng = rng::seeded_rng(seed.unwrap_or_else(rng::random_seed));
+        let mut order: Vec<usize> = (0..examples.len()).collect();
         loop {
             if epochs > 0 {
                 // log error rate if necessary
                 match log_interval {
                     Some(interval) if epochs % interval == 0 => {
-                        println!("error rate: {}", training_error_rate);
+                        let mut handled = false;
+                        if let Some(ref mut callback) = on_epoch {
+                            callback(epochs, training_error_rate);
+                            handled = true;
+                        }
+                        if let Some(ref mut callback) = on_error {
+                            callback(training_error_rate);
+                            handled = true;
+                        }
+                        if !handled {
+                            println!("error rate: {}", training_error_rate);
+                        }
                     },
                     _ => (),
                 }
@@ -343,6 +755,9 @@ impl NN {
                     MSE(target_error) => {
                         if training_error_rate <= target_error { break }
                     },
+                    MedianError(target_error) => {
+                        if median_acc.median() <= target_error { break }
+                    },
                     Timer(duration) => {
                         let now = PreciseTime::now();
                         if start_time.to(now) >= duration { break }
@@ -350,24 +765,40 @@ impl NN {
                 }
             }
 
+            if shuffle {
+                rng.shuffle(&mut order);
+            }
+
             training_error_rate = 0f64;
-            for &(ref inputs, ref targets) in examples.iter() {
-                let results = self.do_run(&inputs);
-                let weight_updates = self.calculate_weight_updates(&results, &targets);
-                training_error_rate += calculate_error(&results, &targets);
-                self.update_weights(&weight_updates, &mut prev_deltas, rate, momentum)
+            median_acc = MedianAccumulator::new();
+            for batch in order.chunks(batch_size) {
+                let mut batch_weight_updates = self.make_weights_tracker(0.0f64);
+                for &index in batch.iter() {
+                    let &(ref inputs, ref targets) = &examples[index];
+                    let (results, sums, masks) = self.do_run_dropout(&inputs, dropout, &mut rng);
+                    let weight_updates = self.calculate_weight_updates(&results, &sums, &targets, loss, Some(&masks));
+                    let example_error = calculate_error(&results, &targets, loss);
+                    training_error_rate += example_error;
+                    median_acc.push(example_error);
+                    sum_weights(&mut batch_weight_updates, weight_updates);
+                }
+                self.update_weights(&batch_weight_updates, &mut prev_deltas, rate, momentum, regularization)
             }
+            training_error_rate += self.regularization_penalty(regularization);
             epochs += 1;
         }
         training_error_rate
     }
 
     fn train_chunk(&mut self, examples: &[(Vec<f64>, Vec<f64>)], rate: f64, momentum: f64, log_interval: Option<u32>,
-                    halt_condition: HaltCondition, num_threads: usize, chunk_size: usize) -> f64 {
+                    halt_condition: HaltCondition, num_threads: usize, chunk_size: usize, loss: Loss,
+                    regularization: Regularization, mut on_epoch: Option<Box<FnMut(u32, f64)>>,
+                    mut on_error: Option<Box<FnMut(f64)>>) -> f64 {
         let mut prev_deltas = self.make_weights_tracker(0.0f64);
         let mut epochs = 0u32;
         let start_time = PreciseTime::now();
         let mut training_error_rate = 0f64;
+        let mut median_acc = MedianAccumulator::new();
         let mut pool = Pool::new(num_threads);
         let self_lock = RwLock::new(self);
 
@@ -376,7 +807,18 @@ impl NN {
                 // log error rate if necessary
                 match log_interval {
                     Some(interval) if epochs % interval == 0 => {
-                        println!("error rate: {}", training_error_rate);
+                        let mut handled = false;
+                        if let Some(ref mut callback) = on_epoch {
+                            callback(epochs, training_error_rate);
+                            handled = true;
+                        }
+                        if let Some(ref mut callback) = on_error {
+                            callback(training_error_rate);
+                            handled = true;
+                        }
+                        if !handled {
+                            println!("error rate: {}", training_error_rate);
+                        }
                     },
                     _ => (),
                 }
@@ -389,6 +831,9 @@ impl NN {
                     MSE(target_error) => {
                         if training_error_rate <= target_error { break }
                     },
+                    MedianError(target_error) => {
+                        if median_acc.median() <= target_error { break }
+                    },
                     Timer(duration) => {
                         let now = PreciseTime::now();
                         if start_time.to(now) >= duration { break }
@@ -397,16 +842,18 @@ impl NN {
             }
 
             training_error_rate = 0f64;
+            median_acc = MedianAccumulator::new();
 
             let mut error_weights = (0.0, vec![vec![vec![]]]);
             crossbeam::scope(|scope| {
                 for exs in examples.chunks(chunk_size) {
                     error_weights = pool.unordered_map(scope, exs, |&(ref inputs, ref targets)| {
-                        let results = self_lock.read().unwrap().do_run(&inputs);
-                        let weight_updates = self_lock.read().unwrap().calculate_weight_updates(&results, &targets);
-                        (calculate_error(&results, &targets), weight_updates)
+                        let (results, sums) = self_lock.read().unwrap().do_run(&inputs);
+                        let weight_updates = self_lock.read().unwrap().calculate_weight_updates(&results, &sums, &targets, loss, None);
+                        (calculate_error(&results, &targets, loss), weight_updates)
                     }).fold((0.0, self_lock.read().unwrap().make_weights_tracker(0.0)), |(mut orig_err, mut orig_weights), (_, (new_err, new_weights))| {
                         orig_err += new_err;
+                        median_acc.push(new_err);
                         sum_weights(&mut orig_weights, new_weights);
                         (orig_err, orig_weights)
                     });
@@ -414,29 +861,169 @@ impl NN {
                     let (err, ref weight_updates) = error_weights;
                     info!("err={:?}", err);
                     training_error_rate += err;
-                    self_lock.write().unwrap().update_weights(&weight_updates, &mut prev_deltas, rate, momentum);
+                    self_lock.write().unwrap().update_weights(&weight_updates, &mut prev_deltas, rate, momentum, regularization);
                 }
             });
+            training_error_rate += self_lock.read().unwrap().regularization_penalty(regularization);
             epochs += 1;
         }
         training_error_rate
     }
 
-    fn do_run(&self, inputs: &[f64]) -> Vec<Vec<f64>> {
+    // full-batch Rprop training: accumulates the gradient over every example
+    // in an epoch (reusing the same chunked/pooled summation the Chunk
+    // learning mode uses) and applies one adaptive-step-size update per epoch
+    fn train_rprop(&mut self, examples: &[(Vec<f64>, Vec<f64>)], log_interval: Option<u32>,
+                    halt_condition: HaltCondition, num_threads: usize, chunk_size: usize,
+                    params: RpropParams, loss: Loss, mut on_epoch: Option<Box<FnMut(u32, f64)>>,
+                    mut on_error: Option<Box<FnMut(f64)>>) -> f64 {
+        let mut prev_gradient = self.make_weights_tracker(0.0f64);
+        let mut deltas = self.make_weights_tracker(params.delta_init);
+        let mut epochs = 0u32;
+        let start_time = PreciseTime::now();
+        let mut training_error_rate = 0f64;
+        let mut median_acc = MedianAccumulator::new();
+        let mut pool = Pool::new(num_threads);
+        let self_lock = RwLock::new(self);
+
+        loop {
+            if epochs > 0 {
+                // log error rate if necessary
+                match log_interval {
+                    Some(interval) if epochs % interval == 0 => {
+                        let mut handled = false;
+                        if let Some(ref mut callback) = on_epoch {
+                            callback(epochs, training_error_rate);
+                            handled = true;
+                        }
+                        if let Some(ref mut callback) = on_error {
+                            callback(training_error_rate);
+                            handled = true;
+                        }
+                        if !handled {
+                            println!("error rate: {}", training_error_rate);
+                        }
+                    },
+                    _ => (),
+                }
+
+                // check if we've met the halt condition yet
+                match halt_condition {
+                    Epochs(epochs_halt) => {
+                        if epochs == epochs_halt { break }
+                    },
+                    MSE(target_error) => {
+                        if training_error_rate <= target_error { break }
+                    },
+                    MedianError(target_error) => {
+                        if median_acc.median() <= target_error { break }
+                    },
+                    Timer(duration) => {
+                        let now = PreciseTime::now();
+                        if start_time.to(now) >= duration { break }
+                    }
+                }
+            }
+
+            training_error_rate = 0f64;
+            median_acc = MedianAccumulator::new();
+            let mut epoch_gradient = self_lock.read().unwrap().make_weights_tracker(0.0f64);
+
+            let mut error_weights = (0.0, vec![vec![vec![]]]);
+            crossbeam::scope(|scope| {
+                for exs in examples.chunks(chunk_size) {
+                    error_weights = pool.unordered_map(scope, exs, |&(ref inputs, ref targets)| {
+                        let (results, sums) = self_lock.read().unwrap().do_run(&inputs);
+                        let weight_updates = self_lock.read().unwrap().calculate_weight_updates(&results, &sums, &targets, loss, None);
+                        (calculate_error(&results, &targets, loss), weight_updates)
+                    }).fold((0.0, self_lock.read().unwrap().make_weights_tracker(0.0)), |(mut orig_err, mut orig_weights), (_, (new_err, new_weights))| {
+                        orig_err += new_err;
+                        median_acc.push(new_err);
+                        sum_weights(&mut orig_weights, new_weights);
+                        (orig_err, orig_weights)
+                    });
+
+                    let (err, ref weight_updates) = error_weights;
+                    training_error_rate += err;
+                    sum_weights(&mut epoch_gradient, weight_updates.clone());
+                }
+            });
+
+            self_lock.write().unwrap().update_weights_rprop(&epoch_gradient, &mut prev_gradient, &mut deltas, params);
+            epochs += 1;
+        }
+        training_error_rate
+    }
+
+    // returns (activated results per layer including the input layer,
+    // pre-activation sums per layer excluding the input layer); the sums
+    // are needed by activations like ReLU whose derivative depends on the
+    // sign of the raw input rather than on the activated output
+    fn do_run(&self, inputs: &[f64]) -> (Vec<Vec<f64>>, Vec<Vec<f64>>) {
         let mut results = Vec::new();
+        let mut sums = Vec::new();
         results.push(inputs.to_vec());
         for (layer_index, layer) in self.layers.iter().enumerate() {
+            let activation = self.activations[layer_index];
             let mut layer_results = Vec::new();
+            let mut layer_sums = Vec::new();
             for node in layer.iter() {
-                layer_results.push( sigmoid(modified_dotprod(&node, &results[layer_index])) )
+                let sum = modified_dotprod(&node, &results[layer_index]);
+                layer_sums.push(sum);
+                layer_results.push(activation.apply(sum));
             }
             results.push(layer_results);
+            sums.push(layer_sums);
         }
-        results
+        (results, sums)
+    }
+
+    // like `do_run`, but independently zeroes each hidden unit's activation
+    // with probability `dropout` and rescales survivors by `1/(1-dropout)`
+    // (inverted dropout), redrawing the mask fresh for this call; the output
+    // layer is never dropped. `dropout == 0.0` keeps every unit, matching
+    // `do_run`. Used only by training, never by `run`. The returned mask
+    // lets `calculate_weight_updates` exclude dropped units from the
+    // backward pass.
+    fn do_run_dropout<R: Rng>(&self, inputs: &[f64], dropout: f64, rng: &mut R) -> (Vec<Vec<f64>>, Vec<Vec<f64>>, Vec<Vec<bool>>) {
+        let mut results = Vec::new();
+        let mut sums = Vec::new();
+        let mut masks = Vec::new();
+        results.push(inputs.to_vec());
+        let output_layer_index = self.layers.len() - 1;
+        for (layer_index, layer) in self.layers.iter().enumerate() {
+            let activation = self.activations[layer_index];
+            let is_hidden = layer_index != output_layer_index;
+            let mut layer_results = Vec::new();
+            let mut layer_sums = Vec::new();
+            let mut layer_mask = Vec::new();
+            for node in layer.iter() {
+                let sum = modified_dotprod(&node, &results[layer_index]);
+                layer_sums.push(sum);
+                let mut output = activation.apply(sum);
+                let keep = if is_hidden && dropout > 0.0 {
+                    let dropped = rng.gen::<f64>() < dropout;
+                    if dropped {
+                        output = 0.0;
+                    } else {
+                        output /= 1.0 - dropout;
+                    }
+                    !dropped
+                } else {
+                    true
+                };
+                layer_results.push(output);
+                layer_mask.push(keep);
+            }
+            results.push(layer_results);
+            sums.push(layer_sums);
+            masks.push(layer_mask);
+        }
+        (results, sums, masks)
     }
 
     // updates all weights in the network
-    fn update_weights(&mut self, network_weight_updates: &Vec<Vec<Vec<f64>>>, prev_deltas: &mut Vec<Vec<Vec<f64>>>, rate: f64, momentum: f64) {
+    fn update_weights(&mut self, network_weight_updates: &Vec<Vec<Vec<f64>>>, prev_deltas: &mut Vec<Vec<Vec<f64>>>, rate: f64, momentum: f64, regularization: Regularization) {
         for layer_index in 0..self.layers.len() {
             let mut layer = &mut self.layers[layer_index];
             let layer_weight_updates = &network_weight_updates[layer_index];
@@ -446,7 +1033,10 @@ impl NN {
                 for weight_index in 0..node.len() {
                     let weight_update = node_weight_updates[weight_index];
                     let prev_delta = prev_deltas[layer_index][node_index][weight_index];
-                    let delta = (rate * weight_update) + (momentum * prev_delta);
+                    let mut delta = (rate * weight_update) + (momentum * prev_delta);
+                    if weight_index != 0 { // don't penalize the threshold/bias weight
+                        delta += regularization_term(regularization, rate, node[weight_index]);
+                    }
                     node[weight_index] += delta;
                     prev_deltas[layer_index][node_index][weight_index] = delta;
                 }
@@ -455,8 +1045,79 @@ impl NN {
 
     }
 
-    // calculates all weight updates by backpropagation
-    fn calculate_weight_updates(&self, results: &Vec<Vec<f64>>, targets: &[f64]) -> Vec<Vec<Vec<f64>>> {
+    // computes the regularization penalty added to the reported training
+    // error so it reflects the regularized objective, not just the raw loss;
+    // excludes the threshold/bias weight (index 0) of every node
+    fn regularization_penalty(&self, regularization: Regularization) -> f64 {
+        match regularization {
+            Regularization::None => 0f64,
+            Regularization::L1(lambda) => {
+                let mut total = 0f64;
+                for layer in self.layers.iter() {
+                    for node in layer.iter() {
+                        for &weight in node.iter().skip(1) {
+                            total += weight.abs();
+                        }
+                    }
+                }
+                lambda * total
+            },
+            Regularization::L2(lambda) => {
+                let mut total = 0f64;
+                for layer in self.layers.iter() {
+                    for node in layer.iter() {
+                        for &weight in node.iter().skip(1) {
+                            total += weight * weight;
+                        }
+                    }
+                }
+                0.5 * lambda * total
+            },
+        }
+    }
+
+    // updates all weights using Rprop's per-weight adaptive step size,
+    // based only on the sign of the gradient rather than its magnitude
+    fn update_weights_rprop(&mut self, epoch_gradient: &Vec<Vec<Vec<f64>>>, prev_gradient: &mut Vec<Vec<Vec<f64>>>,
+                    deltas: &mut Vec<Vec<Vec<f64>>>, params: RpropParams) {
+        for layer_index in 0..self.layers.len() {
+            let mut layer = &mut self.layers[layer_index];
+            let layer_gradient = &epoch_gradient[layer_index];
+            for node_index in 0..layer.len() {
+                let mut node = &mut layer[node_index];
+                let node_gradient = &layer_gradient[node_index];
+                for weight_index in 0..node.len() {
+                    let mut gradient = node_gradient[weight_index];
+                    let prev = prev_gradient[layer_index][node_index][weight_index];
+                    let mut delta = deltas[layer_index][node_index][weight_index];
+                    let product = gradient * prev;
+
+                    if product > 0.0 {
+                        delta = (delta * params.eta_plus).min(params.delta_max);
+                    } else if product < 0.0 {
+                        delta = (delta * params.eta_minus).max(params.delta_min);
+                        // don't let this epoch's flipped gradient double-penalize the next step
+                        gradient = 0.0;
+                    }
+
+                    if gradient != 0.0 {
+                        // `gradient` here is the accumulated `weight_update` from
+                        // `calculate_weight_updates`, i.e. already `-dE/dw`, so
+                        // ascending its sign descends the error
+                        node[weight_index] += gradient.signum() * delta;
+                    }
+                    deltas[layer_index][node_index][weight_index] = delta;
+                    prev_gradient[layer_index][node_index][weight_index] = gradient;
+                }
+            }
+        }
+    }
+
+    // calculates all weight updates by backpropagation; `dropout_masks`, if
+    // given, excludes dropped hidden units (marked `false`) from the
+    // backward pass by forcing their error signal to zero, which also
+    // correctly zeroes their contribution to earlier layers' errors
+    fn calculate_weight_updates(&self, results: &Vec<Vec<f64>>, sums: &Vec<Vec<f64>>, targets: &[f64], loss: Loss, dropout_masks: Option<&Vec<Vec<bool>>>) -> Vec<Vec<Vec<f64>>> {
         let layers = &self.layers;
         let mut network_errors:Vec<Vec<f64>> = Vec::with_capacity(layers.len());
         let mut network_weight_updates = Vec::with_capacity(layers.len());
@@ -465,25 +1126,34 @@ impl NN {
 
         for (layer_index, (layer_nodes, layer_results)) in iter_zip_enum(layers, network_results).rev() {
             let prev_layer_results = &results[layer_index];
+            let layer_sums = &sums[layer_index];
+            let activation = self.activations[layer_index];
             let mut layer_errors = Vec::with_capacity(layer_results.len());
             let mut layer_weight_updates = Vec::with_capacity(layer_results.len());
 
 
             for (node_index, (node, &result)) in iter_zip_enum(layer_nodes, layer_results) {
                 let mut node_weight_updates = Vec::with_capacity(node.len());
+                // `result` may have been zeroed or rescaled by dropout, so the
+                // derivative is taken at the plain activated value of the sum
+                let deriv = activation.derivative(activation.apply(layer_sums[node_index]), layer_sums[node_index]);
 
                 // calculate error for this node
                 let node_error = match layer_index {
-                    s if s == layers.len() -1 => result * (1f64 - result) * (targets[node_index] - result),
+                    s if s == layers.len() -1 => loss.output_delta(deriv, result, targets[node_index]),
                     _ => {
                         let mut sum = 0f64;
                         let next_layer_errors = &network_errors[network_errors.len() - 1];
                         for (next_node, &next_node_error_data) in next_layer_nodes.unwrap().iter().zip((next_layer_errors).iter()) {
                             sum += next_node[node_index+1] * next_node_error_data; // +1 because the 0th weight is the threshold
                         }
-                        result * (1f64 - result) * sum
+                        deriv * sum
                     }
                 };
+                let node_error = match dropout_masks {
+                    Some(masks) if layer_index != layers.len() - 1 && !masks[layer_index][node_index] => 0f64,
+                    _ => node_error,
+                };
 
                 // calculate weight updates for this node
                 for weight_index in 0..node.len() {
@@ -537,11 +1207,6 @@ fn modified_dotprod(node: &Vec<f64>, values: &Vec<f64>) -> f64 {
     total
 }
 
-fn sigmoid(y: f64) -> f64 {
-    1f64 / (1f64 + (-y).exp())
-}
-
-
 // takes two arrays and enumerates the iterator produced by zipping each of
 // their iterators together
 fn iter_zip_enum<'s, 't, S: 's, T: 't>(s: &'s [S], t: &'t [T]) ->
@@ -549,14 +1214,146 @@ fn iter_zip_enum<'s, 't, S: 's, T: 't>(s: &'s [S], t: &'t [T]) ->
     s.iter().zip(t.iter()).enumerate()
 }
 
-// calculates MSE of output layer
-fn calculate_error(results: &Vec<Vec<f64>>, targets: &[f64]) -> f64 {
+// calculates the error of the output layer using the active loss function
+fn calculate_error(results: &Vec<Vec<f64>>, targets: &[f64], loss: Loss) -> f64 {
     let ref last_results = results[results.len()-1];
-    let mut total:f64 = 0f64;
-    for (&result, &target) in last_results.iter().zip(targets.iter()) {
-        total += (target - result).powi(2);
+    loss.error(last_results, targets)
+}
+
+// weight-decay term added to a weight's update under the active regularization
+fn regularization_term(regularization: Regularization, rate: f64, weight: f64) -> f64 {
+    match regularization {
+        Regularization::None => 0f64,
+        Regularization::L1(lambda) => -rate * lambda * weight.signum(),
+        Regularization::L2(lambda) => -rate * lambda * weight,
+    }
+}
+
+fn write_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.push(value as u8);
+    bytes.push((value >> 8) as u8);
+    bytes.push((value >> 16) as u8);
+    bytes.push((value >> 24) as u8);
+}
+
+fn read_u32(bytes: &[u8], cursor: &mut usize) -> Result<u32, String> {
+    if *cursor + 4 > bytes.len() {
+        return Err("truncated while reading a u32".to_string());
+    }
+    let value = (bytes[*cursor] as u32)
+        | ((bytes[*cursor + 1] as u32) << 8)
+        | ((bytes[*cursor + 2] as u32) << 16)
+        | ((bytes[*cursor + 3] as u32) << 24);
+    *cursor += 4;
+    Ok(value)
+}
+
+fn write_f64(bytes: &mut Vec<u8>, value: f64) {
+    let bits: u64 = unsafe { mem::transmute(value) };
+    for i in 0..8 {
+        bytes.push((bits >> (i * 8)) as u8);
+    }
+}
+
+fn read_f64(bytes: &[u8], cursor: &mut usize) -> Result<f64, String> {
+    if *cursor + 8 > bytes.len() {
+        return Err("truncated while reading an f64".to_string());
+    }
+    let mut bits: u64 = 0;
+    for i in 0..8 {
+        bits |= (bytes[*cursor + i] as u64) << (i * 8);
+    }
+    *cursor += 8;
+    Ok(unsafe { mem::transmute(bits) })
+}
+
+// Vitter's Algorithm R: uniformly samples up to `k` examples out of a
+// stream of unknown length without ever holding the whole stream in memory.
+// The first `k` examples fill the reservoir directly; each later example,
+// at 0-indexed position `i` in the stream (so the `i+1`-th overall),
+// replaces a uniformly chosen reservoir slot with probability `k/(i+1)`,
+// and is discarded otherwise.
+fn reservoir_sample<I: Iterator<Item = (Vec<f64>, Vec<f64>)>, R: Rng>(stream: I, k: usize, rng: &mut R) -> Vec<(Vec<f64>, Vec<f64>)> {
+    let mut reservoir = Vec::with_capacity(k);
+    for (i, example) in stream.enumerate() {
+        if i < k {
+            reservoir.push(example);
+        } else {
+            let j = rng.gen_range(0, i + 1);
+            if j < k {
+                reservoir[j] = example;
+            }
+        }
+    }
+    reservoir
+}
+
+// wraps an f64 so it can be used in a `BinaryHeap`, which requires `Ord`;
+// errors are never NaN in practice, so the `None` that `partial_cmp` gives
+// for a NaN falls back to `Equal` rather than panicking
+#[derive(PartialEq)]
+struct OrdF64(f64);
+
+impl Eq for OrdF64 {}
+
+impl PartialOrd for OrdF64 {
+    fn partial_cmp(&self, other: &OrdF64) -> Option<Ordering> {
+        self.0.partial_cmp(&other.0)
+    }
+}
+
+impl Ord for OrdF64 {
+    fn cmp(&self, other: &OrdF64) -> Ordering {
+        self.partial_cmp(other).unwrap_or(Ordering::Equal)
+    }
+}
+
+// streaming two-heap median accumulator backing `HaltCondition::MedianError`:
+// `lower_half` is a max-heap holding values at or below the running median;
+// `upper_half` is a min-heap (a max-heap of negated values) holding values
+// above it. The heaps are rebalanced after every push so their sizes never
+// differ by more than one, which keeps the median an O(1) peek away.
+struct MedianAccumulator {
+    lower_half: BinaryHeap<OrdF64>,
+    upper_half: BinaryHeap<OrdF64>,
+}
+
+impl MedianAccumulator {
+    fn new() -> MedianAccumulator {
+        MedianAccumulator { lower_half: BinaryHeap::new(), upper_half: BinaryHeap::new() }
+    }
+
+    fn push(&mut self, value: f64) {
+        let fits_lower = match self.lower_half.peek() {
+            Some(&OrdF64(top)) => value <= top,
+            None => true,
+        };
+        if fits_lower {
+            self.lower_half.push(OrdF64(value));
+        } else {
+            self.upper_half.push(OrdF64(-value));
+        }
+
+        if self.lower_half.len() > self.upper_half.len() + 1 {
+            let OrdF64(moved) = self.lower_half.pop().unwrap();
+            self.upper_half.push(OrdF64(-moved));
+        } else if self.upper_half.len() > self.lower_half.len() + 1 {
+            let OrdF64(moved) = self.upper_half.pop().unwrap();
+            self.lower_half.push(OrdF64(-moved));
+        }
+    }
+
+    fn median(&self) -> f64 {
+        match self.lower_half.len().cmp(&self.upper_half.len()) {
+            Ordering::Greater => (self.lower_half.peek().unwrap().0),
+            Ordering::Less => -(self.upper_half.peek().unwrap().0),
+            Ordering::Equal => {
+                let lower_top = self.lower_half.peek().map(|&OrdF64(v)| v).unwrap_or(0.0);
+                let upper_top = self.upper_half.peek().map(|&OrdF64(v)| -v).unwrap_or(0.0);
+                (lower_top + upper_top) / 2.0
+            },
+        }
     }
-    total / (last_results.len() as f64)
 }
 
 fn sum_weights(orig_weights: &mut Vec<Vec<Vec<f64>>>, new_weights: Vec<Vec<Vec<f64>>>) {
@@ -568,3 +1365,83 @@ fn sum_weights(orig_weights: &mut Vec<Vec<Vec<f64>>>, new_weights: Vec<Vec<Vec<f
         }).last();
     }).last();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rprop_converges_on_xor() {
+        let examples = [
+            (vec![0f64, 0f64], vec![0f64]),
+            (vec![0f64, 1f64], vec![1f64]),
+            (vec![1f64, 0f64], vec![1f64]),
+            (vec![1f64, 1f64], vec![0f64]),
+        ];
+
+        let mut net = NN::new_seeded(&[2, 3, 1], 42);
+        let final_error = net.train(&examples)
+            .halt_condition(HaltCondition::Epochs(300))
+            .train_algorithm(TrainAlgorithm::Rprop(RpropParams::default()))
+            .go();
+
+        assert!(final_error < 0.5, "rprop should converge on XOR, got final error {}", final_error);
+    }
+
+    #[test]
+    fn to_bytes_from_bytes_round_trip() {
+        let net = NN::new_seeded(&[2, 3, 1], 7)
+            .activation(0, Activation::Tanh)
+            .activation(1, Activation::Sigmoid);
+
+        let bytes = net.to_bytes();
+        let decoded = NN::from_bytes(&bytes).unwrap();
+
+        assert_eq!(decoded.layers_sizes(), net.layers_sizes());
+        assert_eq!(decoded.activations, net.activations);
+        assert_eq!(decoded.layers, net.layers);
+    }
+
+    #[test]
+    fn reservoir_sample_keeps_every_item_when_stream_is_smaller_than_k() {
+        let stream = vec![
+            (vec![0f64], vec![0f64]),
+            (vec![1f64], vec![1f64]),
+        ];
+        let mut rng = rng::seeded_rng(1);
+
+        let sampled = reservoir_sample(stream.clone().into_iter(), 5, &mut rng);
+
+        assert_eq!(sampled, stream);
+    }
+
+    #[test]
+    fn reservoir_sample_keeps_reservoir_at_k_when_stream_is_larger() {
+        let stream = (0..100).map(|i| (vec![i as f64], vec![i as f64]));
+        let mut rng = rng::seeded_rng(1);
+
+        let sampled = reservoir_sample(stream, 10, &mut rng);
+
+        assert_eq!(sampled.len(), 10);
+        for (inputs, _) in sampled.iter() {
+            assert!(inputs[0] >= 0.0 && inputs[0] < 100.0);
+        }
+    }
+
+    #[test]
+    fn median_accumulator_tracks_running_median() {
+        let mut acc = MedianAccumulator::new();
+
+        acc.push(3.0);
+        assert_eq!(acc.median(), 3.0);
+
+        acc.push(1.0);
+        assert_eq!(acc.median(), 2.0); // (1, 3) -> average of the two middles
+
+        acc.push(2.0);
+        assert_eq!(acc.median(), 2.0); // (1, 2, 3) -> 2 is the true middle
+
+        acc.push(100.0);
+        assert_eq!(acc.median(), 2.5); // (1, 2, 3, 100) -> average of 2 and 3
+    }
+}
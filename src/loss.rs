@@ -0,0 +1,70 @@
+//! Loss functions selectable on a `Trainer`.
+
+/// Minimum/maximum prediction allowed before taking a log, to avoid
+/// `ln(0)` blowing up to `-inf` on a perfectly wrong/right prediction.
+const EPSILON: f64 = 1e-15;
+
+/// A loss function used both to report training error and to drive the
+/// output layer's gradient during backpropagation.
+#[derive(Debug, Copy, Clone, PartialEq, RustcDecodable, RustcEncodable)]
+pub enum Loss {
+    /// Mean squared error: `mean((target - output)^2)`
+    MeanSquared,
+    /// Binary cross-entropy, for sigmoid outputs treated as independent
+    /// probabilities (single or multi-label)
+    BinaryCrossEntropy,
+}
+
+impl Loss {
+    /// Computes the loss over a single example's output layer.
+    pub fn error(&self, results: &[f64], targets: &[f64]) -> f64 {
+        match *self {
+            Loss::MeanSquared => {
+                let mut total = 0f64;
+                for (&result, &target) in results.iter().zip(targets.iter()) {
+                    total += (target - result).powi(2);
+                }
+                total / (results.len() as f64)
+            },
+            Loss::BinaryCrossEntropy => {
+                let mut total = 0f64;
+                for (&result, &target) in results.iter().zip(targets.iter()) {
+                    let p = clip(result);
+                    total += -(target * p.ln() + (1f64 - target) * (1f64 - p).ln());
+                }
+                total / (results.len() as f64)
+            },
+        }
+    }
+
+    /// The output layer's error term for a single output node, given that
+    /// node's activation derivative, output and target. This is the
+    /// negated gradient `-dLoss/dSum` (for `MeanSquared`, without the
+    /// constant `2/n` factor), the direction backpropagation steps in.
+    /// Both variants apply the full chain rule (`-dLoss/dOutput *
+    /// dOutput/dSum`), so this stays correct for whatever `Activation` the
+    /// output layer is actually using, not just the one each loss is
+    /// usually paired with. For `BinaryCrossEntropy` this happens to
+    /// algebraically cancel down to `activation_derivative * (target -
+    /// output) / (output * (1 - output))`, which collapses to `target -
+    /// output` only when the output layer's activation is `Sigmoid`.
+    pub fn output_delta(&self, activation_derivative: f64, output: f64, target: f64) -> f64 {
+        match *self {
+            Loss::MeanSquared => activation_derivative * (target - output),
+            Loss::BinaryCrossEntropy => {
+                let p = clip(output);
+                activation_derivative * (target - p) / (p * (1f64 - p))
+            },
+        }
+    }
+}
+
+impl Default for Loss {
+    fn default() -> Loss {
+        Loss::MeanSquared
+    }
+}
+
+fn clip(p: f64) -> f64 {
+    p.max(EPSILON).min(1f64 - EPSILON)
+}
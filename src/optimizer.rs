@@ -0,0 +1,49 @@
+//! Training algorithms selectable on a `Trainer`, alongside the default
+//! rate/momentum SGD.
+
+/// Selects the algorithm used to turn computed gradients into weight
+/// updates.
+#[derive(Debug, Copy, Clone)]
+pub enum TrainAlgorithm {
+    /// Plain rate/momentum stochastic gradient descent, run according to
+    /// the `Trainer`'s `LearningMode` (the crate's original behavior)
+    Backprop,
+    /// Resilient backpropagation: a full-batch method that adapts a
+    /// per-weight step size from the sign of the gradient instead of
+    /// scaling a single global learning rate
+    Rprop(RpropParams),
+}
+
+impl Default for TrainAlgorithm {
+    fn default() -> TrainAlgorithm {
+        TrainAlgorithm::Backprop
+    }
+}
+
+/// Tuning parameters for `TrainAlgorithm::Rprop`. The defaults match the
+/// values from the original Rprop paper (and FANN's implementation).
+#[derive(Debug, Copy, Clone)]
+pub struct RpropParams {
+    /// initial per-weight step size
+    pub delta_init: f64,
+    /// minimum per-weight step size
+    pub delta_min: f64,
+    /// maximum per-weight step size
+    pub delta_max: f64,
+    /// step size growth factor applied when the gradient keeps its sign
+    pub eta_plus: f64,
+    /// step size shrink factor applied when the gradient changes sign
+    pub eta_minus: f64,
+}
+
+impl Default for RpropParams {
+    fn default() -> RpropParams {
+        RpropParams {
+            delta_init: 0.1,
+            delta_min: 1e-6,
+            delta_max: 50.0,
+            eta_plus: 1.2,
+            eta_minus: 0.5,
+        }
+    }
+}
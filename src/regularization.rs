@@ -0,0 +1,19 @@
+//! Weight regularization selectable on a `Trainer`, to combat overfitting.
+
+/// Penalizes large weights during training. The threshold/bias weight
+/// (index `0` of every node) is never penalized.
+#[derive(Debug, Copy, Clone)]
+pub enum Regularization {
+    /// No regularization (default)
+    None,
+    /// L1 (lasso) regularization with the given `lambda`; encourages sparse weights
+    L1(f64),
+    /// L2 (ridge) regularization with the given `lambda`; encourages small weights
+    L2(f64),
+}
+
+impl Default for Regularization {
+    fn default() -> Regularization {
+        Regularization::None
+    }
+}
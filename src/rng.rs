@@ -0,0 +1,27 @@
+//! Helpers for building a seeded `XorShiftRng`, used by `NN::new_seeded`
+//! and by any `Trainer` stochastic step (shuffling, dropout, ...) that
+//! opts into reproducibility via `Trainer::seed`.
+
+use rand;
+use rand::{Rng, SeedableRng, XorShiftRng};
+
+/// Builds a seeded `XorShiftRng` from a single `u64` seed, so that
+/// repeated calls with the same seed produce bit-identical output.
+/// `XorShiftRng` rejects an all-zero state, so the seed's halves are mixed
+/// with fixed non-zero constants (a zero seed still gives a non-zero
+/// state), with `[1, 2, 3, 4]` kept as a guard should the mix ever be zero.
+pub fn seeded_rng(seed: u64) -> XorShiftRng {
+    let lo = seed as u32;
+    let hi = (seed >> 32) as u32;
+    let mut state = [lo ^ 0x9E3779B9, hi ^ 0x243F6A88, lo.wrapping_add(0x85EBCA6B), hi.wrapping_add(0xC2B2AE35)];
+    if state == [0, 0, 0, 0] {
+        state = [1, 2, 3, 4];
+    }
+    XorShiftRng::from_seed(state)
+}
+
+/// Draws a fresh `u64` from the global thread RNG, used to seed a
+/// `XorShiftRng` when the caller didn't ask for a specific seed.
+pub fn random_seed() -> u64 {
+    rand::thread_rng().gen()
+}